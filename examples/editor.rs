@@ -1,3 +1,4 @@
+use std::any::TypeId;
 use std::path::PathBuf;
 
 use bevy::{
@@ -5,12 +6,14 @@ use bevy::{
     diagnostic::FrameTimeDiagnosticsPlugin,
     ecs::system::Command,
     prelude::*,
-    reflect::{erased_serde::private::serde::de::DeserializeSeed, TypeRegistry},
-    render::pipeline::IndexFormat,
-    scene::serde::SceneDeserializer,
+    reflect::{erased_serde::private::serde::de::DeserializeSeed, ReflectComponent, TypeRegistry},
+    render::{pipeline::IndexFormat, render_graph::base::camera::CAMERA_3D},
+    scene::{serde::SceneDeserializer, DynamicEntity},
+    utils::HashSet,
     wgpu::{WgpuFeature, WgpuFeatures, WgpuOptions},
 };
 use bevy_editor_pls::{extensions::EditorExtensionSpawn, EditorPlugin, EditorSettings};
+use bevy_mod_picking::Selection;
 use derive_more::{Deref, DerefMut};
 
 fn editor_settings() -> EditorSettings {
@@ -27,8 +30,169 @@ fn editor_settings() -> EditorSettings {
 struct OpenScene(Option<PathBuf>);
 #[derive(Default, Deref, DerefMut)]
 struct ChangeSinceLastSave(bool);
+/// The entity currently selected in the inspector, if any.
+#[derive(Default, Deref, DerefMut)]
+struct SelectedEntity(Option<Entity>);
+
+/// Mirror `auto_pickable`'s picking selection into [`SelectedEntity`] so the rest of the
+/// example (Ctrl+D clone, the transform gizmo) can act on whatever the user picked in the
+/// viewport/inspector. The `Selection` component is the editor's source of truth for what is
+/// selected.
+fn track_selection(selections: Query<(Entity, &Selection)>, mut selected: ResMut<SelectedEntity>) {
+    let current = selections.iter().find(|(_, selection)| selection.selected()).map(|(entity, _)| entity);
+    if **selected != current {
+        **selected = current;
+    }
+}
+
+/// Marker for entities spawned by the editor itself (camera, light, transform-gizmo
+/// handles). These are excluded from saved scenes so the `.scn.ron` only contains the
+/// user's content.
+struct EditorOnly;
+
+/// Controls which entities and component types [`SaveCommand`] writes out.
+///
+/// Entities carrying any of the `excluded_markers` component types are skipped
+/// entirely; `excluded_types` are stripped from every entity that *is* saved.
+struct SaveFilter {
+    excluded_markers: HashSet<TypeId>,
+    excluded_types: HashSet<TypeId>,
+}
+
+impl Default for SaveFilter {
+    fn default() -> Self {
+        let mut excluded_markers = HashSet::default();
+        excluded_markers.insert(TypeId::of::<EditorOnly>());
+        SaveFilter {
+            excluded_markers,
+            excluded_types: HashSet::default(),
+        }
+    }
+}
+
+/// Fired once a scene has been written to disk so user systems can react.
+struct SceneSaved;
+
+/// Build a [`DynamicScene`] honoring `filter`, instead of serializing the whole world.
+fn build_filtered_scene(world: &World, filter: &SaveFilter, type_registry: &TypeRegistry) -> DynamicScene {
+    let mut scene = DynamicScene::default();
+    let type_registry = type_registry.read();
+    let components = world.components();
+
+    for archetype in world.archetypes().iter() {
+        let type_id_of = |component_id| components.get_info(component_id).and_then(|info| info.type_id());
+
+        let editor_only = archetype
+            .components()
+            .filter_map(type_id_of)
+            .any(|type_id| filter.excluded_markers.contains(&type_id));
+        if editor_only {
+            continue;
+        }
+
+        let entities_offset = scene.entities.len();
+        for entity in archetype.entities() {
+            scene.entities.push(DynamicEntity {
+                entity: entity.id(),
+                components: Vec::new(),
+            });
+        }
+
+        for component_id in archetype.components() {
+            let type_id = match type_id_of(component_id) {
+                Some(type_id) if !filter.excluded_types.contains(&type_id) => type_id,
+                _ => continue,
+            };
+            if let Some(reflect_component) = type_registry.get(type_id).and_then(|r| r.data::<ReflectComponent>()) {
+                for (i, entity) in archetype.entities().iter().enumerate() {
+                    if let Some(component) = reflect_component.reflect_component(world, *entity) {
+                        scene.entities[entities_offset + i].components.push(component.clone_value());
+                    }
+                }
+            }
+        }
+    }
+
+    scene
+}
+
+/// A reversible editor operation.
+///
+/// Unlike Bevy's fire-and-forget [`Command`], every `EditorCommand` knows how to
+/// undo itself: `apply` performs the edit and stashes whatever snapshot data is
+/// needed so that a later `revert` can restore the previous world state.
+trait EditorCommand: Send + Sync + 'static {
+    fn apply(&mut self, world: &mut World);
+    fn revert(&mut self, world: &mut World);
+}
+
+/// Undo/redo history for [`EditorCommand`]s.
+///
+/// `commands[..cursor]` have been applied; `commands[cursor..]` have been
+/// reverted and are available to redo. Executing a fresh command truncates the
+/// redo tail.
+#[derive(Default)]
+struct CommandStack {
+    commands: Vec<Box<dyn EditorCommand>>,
+    cursor: usize,
+}
+
+impl CommandStack {
+    fn execute(&mut self, mut command: Box<dyn EditorCommand>, world: &mut World) {
+        self.commands.truncate(self.cursor);
+        command.apply(world);
+        self.commands.push(command);
+        self.cursor += 1;
+    }
+
+    fn undo(&mut self, world: &mut World) {
+        if self.cursor == 0 {
+            return;
+        }
+        self.cursor -= 1;
+        self.commands[self.cursor].revert(world);
+    }
+
+    fn redo(&mut self, world: &mut World) {
+        if self.cursor == self.commands.len() {
+            return;
+        }
+        self.commands[self.cursor].apply(world);
+        self.cursor += 1;
+    }
+}
+
+/// Bevy command that pushes an [`EditorCommand`] onto the [`CommandStack`] and applies it.
+struct PushCommand(Box<dyn EditorCommand>);
+struct UndoCommand;
+struct RedoCommand;
+
+impl Command for PushCommand {
+    fn write(self: Box<Self>, world: &mut World) {
+        world.resource_scope(|world, mut stack: Mut<CommandStack>| {
+            stack.execute(self.0, world);
+        });
+    }
+}
+
+impl Command for UndoCommand {
+    fn write(self: Box<Self>, world: &mut World) {
+        world.resource_scope(|world, mut stack: Mut<CommandStack>| stack.undo(world));
+    }
+}
+
+impl Command for RedoCommand {
+    fn write(self: Box<Self>, world: &mut World) {
+        world.resource_scope(|world, mut stack: Mut<CommandStack>| stack.redo(world));
+    }
+}
 
 fn main() {
+    if std::env::args().any(|arg| arg == "--version" || arg == "-v") {
+        println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+        return;
+    }
+
     App::build()
         .insert_resource(WgpuOptions {
             features: WgpuFeatures {
@@ -45,11 +209,21 @@ fn main() {
         .add_plugin(EditorExtensionSpawn)
         .init_resource::<OpenScene>()
         .init_resource::<ChangeSinceLastSave>()
+        .init_resource::<CommandStack>()
+        .init_resource::<SaveFilter>()
+        .init_resource::<SelectedEntity>()
+        .init_resource::<GizmoState>()
+        .add_event::<SceneSaved>()
         .add_startup_system(bevy_editor_pls::setup_default_keybindings.system())
         // systems
         .add_startup_system(setup.system())
+        .add_startup_system(open_scene_from_args.exclusive_system())
         .add_system(title_adjust.system())
+        .add_system(track_selection.system())
         .add_system(save.system())
+        .add_system(transform_gizmo.system())
+        .add_system(update_gizmo_handles.system())
+        .track_dirty::<Transform>()
         .run();
 }
 
@@ -65,7 +239,7 @@ fn title_adjust(open_path: Res<OpenScene>, mut windows: ResMut<Windows>, csls: R
     ))
 }
 
-fn save(input: Res<Input<KeyCode>>, mut commands: Commands, mut open_scene: ResMut<OpenScene>) {
+fn save(input: Res<Input<KeyCode>>, mut commands: Commands, mut open_scene: ResMut<OpenScene>, selected: Res<SelectedEntity>) {
     if input.pressed(KeyCode::LControl) {
         if input.just_pressed(KeyCode::S) {
             if input.pressed(KeyCode::LShift) {
@@ -80,6 +254,15 @@ fn save(input: Res<Input<KeyCode>>, mut commands: Commands, mut open_scene: ResM
             commands.add(SaveCommand);
         } else if input.just_pressed(KeyCode::O) {
             commands.add(OpenCommand);
+        } else if input.just_pressed(KeyCode::Z) {
+            commands.add(UndoCommand);
+        } else if input.just_pressed(KeyCode::Y) {
+            commands.add(RedoCommand);
+        } else if input.just_pressed(KeyCode::D) {
+            if let Some(source) = **selected {
+                let destination = commands.spawn().id();
+                commands.add(CloneEntity { source, destination });
+            }
         }
     }
 }
@@ -87,10 +270,115 @@ fn save(input: Res<Input<KeyCode>>, mut commands: Commands, mut open_scene: ResM
 struct SaveCommand;
 struct OpenCommand;
 
+/// What the viewport gizmo manipulates when a selected entity is dragged.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum InteractionMode {
+    None,
+    Translate,
+    Rotate,
+    Scale,
+}
+
+impl Default for InteractionMode {
+    fn default() -> Self {
+        InteractionMode::None
+    }
+}
+
+/// Live state of the transform gizmo.
+///
+/// `axis` constrains the manipulation (defaults to X, switched with the X/Y/Z keys).
+/// While a drag is in progress `drag` holds the entity and the `Transform` it had when the
+/// drag began, so the whole drag can be committed as a single reversible [`TransformCommand`].
+#[derive(Default)]
+struct GizmoState {
+    mode: InteractionMode,
+    axis: Vec3,
+    drag: Option<GizmoDrag>,
+}
+
+struct GizmoDrag {
+    entity: Entity,
+    start: Transform,
+    last_cursor: Vec2,
+}
+
+/// Marker on the three axis-handle meshes drawn by the gizmo.
+struct GizmoHandle;
+
+/// Half-length of a handle mesh, in world units; also the reach of handle hit-testing.
+const HANDLE_LENGTH: f32 = 1.0;
+/// Thickness of a handle mesh.
+const HANDLE_THICKNESS: f32 = 0.03;
+/// How close (world units) the cursor ray must pass to an axis to grab its handle.
+const HANDLE_PICK_RADIUS: f32 = 0.15;
+
+/// Reversible snapshot of a single completed gizmo drag.
+struct TransformCommand {
+    entity: Entity,
+    from: Transform,
+    to: Transform,
+}
+
+impl EditorCommand for TransformCommand {
+    fn apply(&mut self, world: &mut World) {
+        if let Some(mut transform) = world.get_mut::<Transform>(self.entity) {
+            *transform = self.to;
+        }
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        if let Some(mut transform) = world.get_mut::<Transform>(self.entity) {
+            *transform = self.from;
+        }
+    }
+}
+
+/// Deep-copy every reflected component of `source` onto `destination`.
+///
+/// `destination` is expected to be a freshly spawned empty entity so the result is a
+/// copy of `source`. Components whose type has no [`ReflectComponent`] data in the
+/// [`TypeRegistry`] are skipped with a warning, so the copy does not carry them.
+struct CloneEntity {
+    source: Entity,
+    destination: Entity,
+}
+
+impl Command for CloneEntity {
+    fn write(self: Box<Self>, world: &mut World) {
+        let registry = world.get_resource::<TypeRegistry>().unwrap().clone();
+        let registry = registry.read();
+
+        let component_ids = world.entity(self.source).archetype().components().collect::<Vec<_>>();
+        for component_id in component_ids {
+            let info = world.components().get_info(component_id).unwrap();
+            let name = info.name().to_string();
+            let type_id = match info.type_id() {
+                Some(type_id) => type_id,
+                None => continue,
+            };
+            // Skip components with no reflection data (e.g. the picking helpers `Selection`,
+            // `Hover`, `PickableMesh` that every selectable entity carries). They can't be
+            // copied through the registry, but that's no reason to abort the whole clone.
+            let reflect_component = match registry.get(type_id).and_then(|registration| registration.data::<ReflectComponent>()) {
+                Some(reflect_component) => reflect_component,
+                None => {
+                    warn!("skipping component `{}` while cloning: not registered for reflection", name);
+                    continue;
+                }
+            };
+
+            let component = reflect_component.reflect_component(world, self.source).unwrap().clone_value();
+            reflect_component.apply_or_insert(world, self.destination, &*component);
+        }
+    }
+}
+
 impl Command for SaveCommand {
     fn write(self: Box<Self>, world: &mut World) {
-        let type_registry = world.get_resource::<TypeRegistry>().unwrap();
-        let scene = DynamicScene::from_world(&world, &type_registry);
+        let type_registry = world.get_resource::<TypeRegistry>().unwrap().clone();
+        let filter = world.get_resource::<SaveFilter>().unwrap();
+        let scene = build_filtered_scene(world, filter, &type_registry);
 
         let serialized = scene.serialize_ron(&type_registry).unwrap();
         let mut open_scene = world.get_resource_mut::<OpenScene>().unwrap();
@@ -105,6 +393,7 @@ impl Command for SaveCommand {
         }
         std::fs::write(open_scene.as_ref().unwrap(), serialized).unwrap();
         **world.get_resource_mut::<ChangeSinceLastSave>().unwrap() = false;
+        world.get_resource_mut::<Events<SceneSaved>>().unwrap().send(SceneSaved);
     }
 }
 
@@ -116,30 +405,311 @@ impl Command for OpenCommand {
         } else {
             return;
         };
-        let file = std::fs::read(&path).unwrap();
-        let mut deserializer = ron::de::Deserializer::from_bytes(&file).unwrap();
-        let registry = world.get_resource::<TypeRegistry>().unwrap().read();
-        let scene_deserializer = SceneDeserializer {
-            type_registry: &*registry,
-        };
-        let scene = scene_deserializer.deserialize(&mut deserializer).unwrap();
-        drop(registry);
-        let to_despawn = world.query::<Entity>().iter(world).collect::<Vec<_>>();
-        for e in to_despawn {
-            world.entity_mut(e).despawn();
-        }
-        scene.write_to_world(world, &mut Default::default()).unwrap();
-        **world.get_resource_mut::<OpenScene>().unwrap() = Some(path);
-    }
-}
-
-pub fn setup(mut commands: Commands, mut _meshes: ResMut<Assets<Mesh>>, mut _materials: ResMut<Assets<StandardMaterial>>) {
-    commands.spawn_bundle(LightBundle {
-        transform: Transform::from_xyz(4.0, 8.0, 4.0),
-        ..Default::default()
-    });
-    commands.spawn_bundle(PerspectiveCameraBundle {
-        transform: Transform::from_xyz(-2.0, 2.5, 5.0).looking_at(Vec3::ZERO, Vec3::Y),
-        ..Default::default()
-    });
+        load_scene(world, path);
+    }
+}
+
+/// Deserialize the `.scn.ron` at `path`, despawn the current scene (every entity not marked
+/// [`EditorOnly`]), and write the loaded one into the world. Shared by [`OpenCommand`] and
+/// the command-line startup path.
+fn load_scene(world: &mut World, path: PathBuf) {
+    let file = std::fs::read(&path).unwrap();
+    let mut deserializer = ron::de::Deserializer::from_bytes(&file).unwrap();
+    let registry = world.get_resource::<TypeRegistry>().unwrap().read();
+    let scene_deserializer = SceneDeserializer {
+        type_registry: &*registry,
+    };
+    let scene = scene_deserializer.deserialize(&mut deserializer).unwrap();
+    drop(registry);
+    let to_despawn = world.query_filtered::<Entity, Without<EditorOnly>>().iter(world).collect::<Vec<_>>();
+    for e in to_despawn {
+        world.entity_mut(e).despawn();
+    }
+    scene.write_to_world(world, &mut Default::default()).unwrap();
+    **world.get_resource_mut::<OpenScene>().unwrap() = Some(path);
+    // A freshly loaded scene matches what's on disk: clear the dirty marker so the despawn of
+    // the old scene and the add of the new one don't leave a spurious `*` in the title bar.
+    **world.get_resource_mut::<ChangeSinceLastSave>().unwrap() = false;
+}
+
+/// Startup system: if a `.scn.ron` path was passed on the command line, load it in place of
+/// the default scene. Makes the editor usable as a file-manager "open with" target.
+fn open_scene_from_args(world: &mut World) {
+    let path = std::env::args().skip(1).find(|arg| arg.ends_with(".scn.ron"));
+    if let Some(path) = path {
+        load_scene(world, PathBuf::from(path));
+    }
+}
+
+/// Flip [`ChangeSinceLastSave`] when a tracked component changes on a user entity.
+///
+/// `Changed<T>` covers both edits and spawns (it's true the frame a component is added), so
+/// genuine user spawns — e.g. a Ctrl+D clone — mark the scene dirty too. `EditorOnly`
+/// entities (the flycam camera, light, helpers) are excluded so the editor's own per-frame
+/// systems — flycam movement, diagnostics — don't raise false positives. Frames in which
+/// [`OpenScene`] changed are skipped, so the entities a [`load_scene`] just wrote don't mark
+/// the freshly loaded scene dirty.
+fn dirty_on_change<T: Component>(
+    query: Query<(), (Changed<T>, Without<EditorOnly>)>,
+    open_scene: Res<OpenScene>,
+    mut csls: ResMut<ChangeSinceLastSave>,
+) {
+    if open_scene.is_changed() {
+        return;
+    }
+    if query.iter().next().is_some() {
+        **csls = true;
+    }
+}
+
+/// Flip [`ChangeSinceLastSave`] when a tracked component is removed, i.e. an entity carrying
+/// it was despawned or edited away. Like [`dirty_on_change`], it skips frames in which
+/// [`OpenScene`] changed, so despawning the old scene during a load doesn't count.
+fn dirty_on_despawn<T: Component>(
+    removed: RemovedComponents<T>,
+    open_scene: Res<OpenScene>,
+    mut csls: ResMut<ChangeSinceLastSave>,
+) {
+    if open_scene.is_changed() {
+        return;
+    }
+    if removed.iter().next().is_some() {
+        **csls = true;
+    }
+}
+
+/// Registers which component types count as "meaningful" edits for dirty-state tracking.
+///
+/// Like [`SaveFilter`], this works per component type, but here the user opts specific types
+/// in rather than every mutation flipping the indicator.
+trait DirtyTrackingAppExt {
+    fn track_dirty<T: Component>(&mut self) -> &mut Self;
+}
+
+impl DirtyTrackingAppExt for AppBuilder {
+    fn track_dirty<T: Component>(&mut self) -> &mut Self {
+        self.add_system(dirty_on_change::<T>.system())
+            .add_system(dirty_on_despawn::<T>.system())
+    }
+}
+
+/// World-space ray `(origin, direction)` through `cursor` for the given camera.
+fn cursor_ray(camera: &Camera, camera_transform: &GlobalTransform, window: &Window, cursor: Vec2) -> (Vec3, Vec3) {
+    let screen_size = Vec2::new(window.width(), window.height());
+    let ndc = (cursor / screen_size) * 2.0 - Vec2::ONE;
+    let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix.inverse();
+    let near = ndc_to_world.project_point3(ndc.extend(0.0));
+    let far = ndc_to_world.project_point3(ndc.extend(1.0));
+    (near, (far - near).normalize())
+}
+
+/// Parameter `t` of the point on the line `origin + t * axis` closest to the given ray, i.e.
+/// the signed distance along `axis` that the cursor ray projects to.
+fn axis_projection(origin: Vec3, axis: Vec3, ray_origin: Vec3, ray_dir: Vec3) -> f32 {
+    let axis = axis.normalize();
+    let b = axis.dot(ray_dir);
+    let denom = 1.0 - b * b;
+    if denom.abs() < 1e-6 {
+        // Ray is (nearly) parallel to the axis: no well-defined projection this frame.
+        return 0.0;
+    }
+    let r = origin - ray_origin;
+    (b * ray_dir.dot(r) - axis.dot(r)) / denom
+}
+
+/// Perpendicular world-space distance from the cursor ray to the axis handle at `origin`.
+/// Used to decide which handle (if any) a click grabbed.
+fn handle_pick_distance(origin: Vec3, axis: Vec3, ray_origin: Vec3, ray_dir: Vec3) -> f32 {
+    let axis = axis.normalize();
+    let point = origin + axis * axis_projection(origin, axis, ray_origin, ray_dir);
+    let closest_on_ray = ray_origin + ray_dir * ray_dir.dot(point - ray_origin);
+    (point - closest_on_ray).length()
+}
+
+/// Drive the translate/rotate/scale gizmo.
+///
+/// G/R/S pick the mode; the axis handles drawn at the selected entity's world position
+/// (see [`update_gizmo_handles`]) are grabbed by clicking them, and X/Y/Z also constrain the
+/// axis. A drag only starts when the click lands on a handle; the cursor ray is then
+/// projected onto that axis using the 3D camera and the resulting delta is applied to the
+/// entity's `Transform` every frame. Releasing commits the whole drag as one
+/// [`TransformCommand`] so it undoes/redoes as a unit.
+#[allow(clippy::too_many_arguments)]
+fn transform_gizmo(
+    mut gizmo: ResMut<GizmoState>,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    selected: Res<SelectedEntity>,
+    mut commands: Commands,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    globals: Query<&GlobalTransform>,
+    mut transforms: Query<&mut Transform>,
+    mut csls: ResMut<ChangeSinceLastSave>,
+) {
+    if keys.just_pressed(KeyCode::G) {
+        gizmo.mode = InteractionMode::Translate;
+    } else if keys.just_pressed(KeyCode::R) {
+        gizmo.mode = InteractionMode::Rotate;
+    } else if keys.just_pressed(KeyCode::S) && !keys.pressed(KeyCode::LControl) {
+        gizmo.mode = InteractionMode::Scale;
+    }
+    if !keys.pressed(KeyCode::LControl) {
+        if keys.just_pressed(KeyCode::X) {
+            gizmo.axis = Vec3::X;
+        } else if keys.just_pressed(KeyCode::Y) {
+            gizmo.axis = Vec3::Y;
+        } else if keys.just_pressed(KeyCode::Z) {
+            gizmo.axis = Vec3::Z;
+        }
+    }
+    if gizmo.axis == Vec3::ZERO {
+        gizmo.axis = Vec3::X;
+    }
+
+    if gizmo.mode == InteractionMode::None {
+        return;
+    }
+
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    let cursor = match window.cursor_position() {
+        Some(cursor) => cursor,
+        None => return,
+    };
+    // Pick the 3D camera specifically; `DefaultPlugins` also spawns a UI/2D camera whose
+    // projection would produce garbage rays.
+    let (camera, camera_transform) = match cameras.iter().find(|(camera, _)| camera.name.as_deref() == Some(CAMERA_3D)) {
+        Some(camera) => camera,
+        None => return,
+    };
+
+    // Begin a drag, but only when the click actually lands on one of the axis handles. The
+    // hit handle decides which axis is manipulated.
+    if mouse.just_pressed(MouseButton::Left) && gizmo.drag.is_none() {
+        if let Some(entity) = **selected {
+            if let Ok(transform) = transforms.get(entity) {
+                // Hit-test around the world position, where `update_gizmo_handles` draws them.
+                let origin = globals.get(entity).map(|global| global.translation).unwrap_or(transform.translation);
+                let (ray_origin, ray_dir) = cursor_ray(camera, camera_transform, window, cursor);
+                let hit = [Vec3::X, Vec3::Y, Vec3::Z]
+                    .iter()
+                    .copied()
+                    .map(|axis| (axis, handle_pick_distance(origin, axis, ray_origin, ray_dir), axis_projection(origin, axis, ray_origin, ray_dir)))
+                    .filter(|(_, distance, t)| *distance <= HANDLE_PICK_RADIUS && t.abs() <= HANDLE_LENGTH)
+                    .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+                if let Some((axis, _, _)) = hit {
+                    gizmo.axis = axis;
+                    gizmo.drag = Some(GizmoDrag {
+                        entity,
+                        start: *transform,
+                        last_cursor: cursor,
+                    });
+                }
+            }
+        }
+    }
+
+    // Apply the per-frame delta by projecting the cursor ray onto the active axis.
+    if let Some(drag) = gizmo.drag.as_mut() {
+        if let Ok(mut transform) = transforms.get_mut(drag.entity) {
+            let origin = transform.translation;
+            let (prev_origin, prev_dir) = cursor_ray(camera, camera_transform, window, drag.last_cursor);
+            let (curr_origin, curr_dir) = cursor_ray(camera, camera_transform, window, cursor);
+            let amount = axis_projection(origin, gizmo.axis, curr_origin, curr_dir) - axis_projection(origin, gizmo.axis, prev_origin, prev_dir);
+            drag.last_cursor = cursor;
+            if amount != 0.0 {
+                match gizmo.mode {
+                    InteractionMode::Translate => transform.translation += gizmo.axis * amount,
+                    InteractionMode::Rotate => transform.rotation *= Quat::from_axis_angle(gizmo.axis, amount),
+                    InteractionMode::Scale => transform.scale += gizmo.axis * amount,
+                    InteractionMode::None => {}
+                }
+                **csls = true;
+            }
+        }
+    }
+
+    // Commit the completed drag as a single reversible command.
+    if mouse.just_released(MouseButton::Left) {
+        if let Some(drag) = gizmo.drag.take() {
+            if let Ok(transform) = transforms.get_mut(drag.entity) {
+                let to = *transform;
+                if to != drag.start {
+                    commands.add(PushCommand(Box::new(TransformCommand {
+                        entity: drag.entity,
+                        from: drag.start,
+                        to,
+                    })));
+                }
+            }
+        }
+    }
+}
+
+pub fn setup(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>, mut materials: ResMut<Assets<StandardMaterial>>) {
+    commands
+        .spawn_bundle(LightBundle {
+            transform: Transform::from_xyz(4.0, 8.0, 4.0),
+            ..Default::default()
+        })
+        .insert(EditorOnly);
+    commands
+        .spawn_bundle(PerspectiveCameraBundle {
+            transform: Transform::from_xyz(-2.0, 2.5, 5.0).looking_at(Vec3::ZERO, Vec3::Y),
+            ..Default::default()
+        })
+        .insert(EditorOnly);
+
+    // Three axis handles for the transform gizmo. They're hidden until an entity is selected
+    // and a mode is active, and follow the selection in `update_gizmo_handles`. The base mesh
+    // is elongated along X; each handle rotates it onto its own axis.
+    let handle_mesh = meshes.add(Mesh::from(shape::Box::new(HANDLE_LENGTH * 2.0, HANDLE_THICKNESS, HANDLE_THICKNESS)));
+    let handles = [
+        (Color::RED, Quat::IDENTITY),
+        (Color::GREEN, Quat::from_rotation_z(std::f32::consts::FRAC_PI_2)),
+        (Color::BLUE, Quat::from_rotation_y(-std::f32::consts::FRAC_PI_2)),
+    ];
+    for (color, rotation) in handles.iter().copied() {
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh: handle_mesh.clone(),
+                material: materials.add(StandardMaterial {
+                    base_color: color,
+                    unlit: true,
+                    ..Default::default()
+                }),
+                transform: Transform {
+                    rotation,
+                    ..Default::default()
+                },
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: false,
+                },
+                ..Default::default()
+            })
+            .insert(EditorOnly)
+            .insert(GizmoHandle);
+    }
+}
+
+/// Keep the gizmo handles at the selected entity's world position and show them only while a
+/// manipulation mode is active with something selected.
+fn update_gizmo_handles(
+    gizmo: Res<GizmoState>,
+    selected: Res<SelectedEntity>,
+    globals: Query<&GlobalTransform>,
+    mut handles: Query<(&mut Transform, &mut Visible, &GizmoHandle)>,
+) {
+    let target = (**selected).and_then(|entity| globals.get(entity).ok()).map(|global| global.translation);
+    let active = gizmo.mode != InteractionMode::None && target.is_some();
+    for (mut transform, mut visible, _) in handles.iter_mut() {
+        visible.is_visible = active;
+        if let Some(position) = target {
+            transform.translation = position;
+        }
+    }
 }